@@ -6,10 +6,13 @@ use base64::Engine;
 
 use gloo::file::callbacks::FileReader;
 use gloo::file::File;
-use gloo_console::log;
 
-use web_sys::{DragEvent, MouseEvent, Event, FileList, HtmlInputElement};
-use wasm_bindgen::JsValue;
+use web_sys::{
+    Blob, BlobPropertyBag, CanvasRenderingContext2d, DragEvent, Event, FileList,
+    HtmlAnchorElement, HtmlCanvasElement, HtmlImageElement, HtmlInputElement, MouseEvent, Url,
+};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
 
 use yew::html::TargetCast;
 use yew::{html, Callback, Component, Context, Html};
@@ -21,7 +24,26 @@ use truchet::{image::Image, vec2::Vec2, svg::node::element::SVG, to_svg::ToSVG};
 struct FileDetails {
     name: String,
     file_type: String,
-    data: Vec<u8>
+    data: Vec<u8>,
+    orientation: u32
+}
+
+struct GeneratedTile {
+    name: String,
+    svg_doc: Vec<u8>,
+    svg_html: String
+}
+
+#[derive(Clone, Copy)]
+struct DitherSettings {
+    enabled: bool,
+    levels: u32
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TileKind {
+    Circle,
+    Triangle
 }
 
 struct ImageAdapter {
@@ -42,19 +64,51 @@ impl Image for ImageAdapter {
     }
 }
 
+/// A single-cell, constant-brightness `Image` used to probe a generator's
+/// output at a given brightness without decoding a real photo.
+struct SolidImage {
+    brightness: f32
+}
+
+impl Image for SolidImage {
+    fn size(&self) -> Vec2<usize> {
+        Vec2::new(1, 1)
+    }
+
+    fn get_pixel_brightness(&self, _pos: Vec2<usize>) -> f32 {
+        self.brightness
+    }
+}
+
 pub enum Msg {
     Loaded(String, String, Vec<u8>),
-    Files(Vec<File>),
+    Files(Vec<File>, Option<String>),
     GenerateButtonClicked(bool),
-    TileDropdownClicked(bool)
+    TileDropdownClicked(bool),
+    TileSelected(TileKind),
+    GridSizeChanged(usize),
+    DownloadSvg(usize),
+    DownloadPng(usize),
+    PngResolutionChanged(u32),
+    DragEnter,
+    DragLeave,
+    DitherToggled(bool),
+    DitherLevelsChanged(u32)
 }
 
 pub struct App {
     readers: HashMap<String, FileReader>,
     files: Vec<FileDetails>,
     tile_dropdown_is_open: bool,
-    tile_dropdown_opened_classes: String,
-    tile_dropdown_closed_classes: String
+    tile_kind: TileKind,
+    grid_size: usize,
+    results: Vec<GeneratedTile>,
+    png_resolution: u32,
+    drag_active: bool,
+    upload_error: Option<String>,
+    dither_enabled: bool,
+    dither_levels: u32,
+    dither_ceiling: u32
 }
 
 impl Component for App {
@@ -62,27 +116,40 @@ impl Component for App {
     type Properties = ();
 
     fn create(_ctx: &Context<Self>) -> Self {
+        let dither_ceiling = Self::tile_state_count(TileKind::Circle);
         Self {
             readers: HashMap::default(),
             files: Vec::default(),
             tile_dropdown_is_open: false,
-            tile_dropdown_opened_classes: classes!("rounded-md","bg-white","focus:outline-none").to_string(),
-            tile_dropdown_closed_classes: classes!("rounded-md","bg-white","focus:outline-none","hidden").to_string(),
+            tile_kind: TileKind::Circle,
+            grid_size: 6,
+            results: Vec::default(),
+            png_resolution: 1024,
+            drag_active: false,
+            upload_error: None,
+            dither_enabled: false,
+            dither_levels: dither_ceiling,
+            dither_ceiling,
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::Loaded(file_name, file_type, data) => {
+                let orientation = Self::read_exif_orientation(&data);
+                let data = Self::strip_exif(data);
                 self.files.push(FileDetails {
                     data,
                     file_type,
                     name: file_name.clone(),
+                    orientation,
                 });
                 self.readers.remove(&file_name);
                 true
             }
-            Msg::Files(files) => {
+            Msg::Files(files, error) => {
+                self.drag_active = false;
+                self.upload_error = error;
                 for file in files.into_iter() {
                     let file_name = file.name();
                     let file_type = file.raw_mime_type();
@@ -106,52 +173,70 @@ impl Component for App {
             Msg::GenerateButtonClicked(bool) => {
                 if self.files.len() == 0 {
                     return false
-                } 
-                if self.files.len() > 0 {
-                    let image = image::load_from_memory(&self.files[0].data);
-                    let grayscale = ImageAdapter::new(image.expect("Should be valid image").into_luma8());
-                    let truchet = truchet::truchet_image::generate(&grayscale, truchet::generator::circles(Vec2::new(6, 6)));
-                    let svg = truchet.to_svg_node();
-                    let g = Group::new()
-                        .set("transform", "scale(10 10)")
-                        .add(svg);
-                    let svg_doc = SVG::new()
-                        .add(g)
-                        .set("height", "500px")
-                        .set("width", "500px");
-                    let mut data = vec![];
-                    svg::write(&mut data, &svg_doc).unwrap();
-                    let data = String::from_utf8(data).unwrap();
-                    let document = web_sys::window()
-                        .unwrap()
-                        .document()
-                        .unwrap();
-                    let output_area = document.get_element_by_id("truchet-output-area").expect("Output area should exist");
-                    output_area.set_inner_html(&data);
                 }
+
+                self.results.clear();
+                let dither = DitherSettings {
+                    enabled: self.dither_enabled,
+                    levels: self.dither_levels,
+                };
+                let mut failed = Vec::new();
+                for file in self.files.iter() {
+                    match Self::generate_tile(file, self.tile_kind, self.grid_size, dither) {
+                        Ok(tile) => self.results.push(tile),
+                        Err(_) => failed.push(file.name.clone()),
+                    }
+                }
+                self.upload_error = (!failed.is_empty())
+                    .then(|| format!("Could not be decoded as an image: {}", failed.join(", ")));
                 true
             }
             Msg::TileDropdownClicked(bool) => {
-                let document = web_sys::window()
-                    .unwrap()
-                    .document()
-                    .unwrap();
-                let dropdown = document.get_element_by_id("tile-dropdown-wrapper")
-                    .expect("Should exist");
-                let mut class_name = dropdown.class_name();
-                match self.tile_dropdown_is_open {
-                    true => {
-                        dropdown.set_class_name(&self.tile_dropdown_opened_classes);
-                        log!("Hello", JsValue::from(dropdown.id()));
-                    },
-                    false => {
-                        dropdown.set_class_name(&self.tile_dropdown_closed_classes);
-                        log!("Hello", JsValue::from(dropdown.id()));
-                    }
-                }
                 self.tile_dropdown_is_open = !self.tile_dropdown_is_open;
                 true
             }
+            Msg::TileSelected(kind) => {
+                self.tile_kind = kind;
+                self.dither_ceiling = Self::tile_state_count(kind);
+                self.dither_levels = self.dither_ceiling;
+                true
+            }
+            Msg::GridSizeChanged(size) => {
+                self.grid_size = size.max(1);
+                true
+            }
+            Msg::DownloadSvg(index) => {
+                if let Some(tile) = self.results.get(index) {
+                    Self::download_svg(&tile.svg_doc);
+                }
+                false
+            }
+            Msg::DownloadPng(index) => {
+                if let Some(tile) = self.results.get(index) {
+                    Self::download_png(&tile.svg_doc, self.png_resolution);
+                }
+                false
+            }
+            Msg::PngResolutionChanged(resolution) => {
+                self.png_resolution = resolution.max(1);
+                true
+            }
+            Msg::DragEnter => {
+                self.drag_active = true;
+                true
+            }
+            Msg::DragLeave => {
+                self.drag_active = false;
+                true
+            }
+            Msg::DitherToggled(enabled) => {
+                self.dither_enabled = enabled;
+                true
+            }
+            Msg::DitherLevelsChanged(levels) => {
+                self.dither_levels = levels.clamp(2, self.dither_ceiling);
+                true
+            }
         }
     }
 
@@ -166,6 +251,39 @@ impl Component for App {
             Msg::GenerateButtonClicked(true)
         });
 
+        let circle_on_click = ctx.link().callback(|event: MouseEvent| {
+            event.prevent_default();
+            Msg::TileSelected(TileKind::Circle)
+        });
+
+        let triangle_on_click = ctx.link().callback(|event: MouseEvent| {
+            event.prevent_default();
+            Msg::TileSelected(TileKind::Triangle)
+        });
+
+        let grid_size_on_change = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let size = input.value().parse::<usize>().unwrap_or(6);
+            Msg::GridSizeChanged(size)
+        });
+
+        let png_resolution_on_change = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let resolution = input.value().parse::<u32>().unwrap_or(1024);
+            Msg::PngResolutionChanged(resolution)
+        });
+
+        let dither_toggled_on_click = ctx.link().callback(|e: MouseEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::DitherToggled(input.checked())
+        });
+
+        let dither_levels_on_change = ctx.link().callback(|e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let levels = input.value().parse::<u32>().unwrap_or(2);
+            Msg::DitherLevelsChanged(levels)
+        });
+
         html! {
             <div id="wrapper-wrapper" class={ classes!("flex","content-center","w-screen","h-screen","p-20") }>
                 <div id="wrapper" class={ classes!("bg-slate-500","flex","flex-row","space-x-10","outline","outline-grey","w-screen","justify-around") }>
@@ -174,6 +292,7 @@ impl Component for App {
                         <label for="file-upload">
                             <div
                                 id="drop-container"
+                                class={ classes!(self.drag_active.then_some("outline-emerald-400")) }
                                 ondrop={ctx.link().callback(|event: DragEvent| {
                                     event.prevent_default();
                                     let files = event.data_transfer().unwrap().files();
@@ -182,8 +301,13 @@ impl Component for App {
                                 ondragover={Callback::from(|event: DragEvent| {
                                     event.prevent_default();
                                 })}
-                                ondragenter={Callback::from(|event: DragEvent| {
+                                ondragenter={ctx.link().callback(|event: DragEvent| {
                                     event.prevent_default();
+                                    Msg::DragEnter
+                                })}
+                                ondragleave={ctx.link().callback(|event: DragEvent| {
+                                    event.prevent_default();
+                                    Msg::DragLeave
                                 })}
                             >
                                 <i class="fa fa-cloud-upload"></i>
@@ -200,6 +324,9 @@ impl Component for App {
                                 Self::upload_files(input.files())
                             })}
                         />
+                        if let Some(error) = &self.upload_error {
+                            <p id="upload-error" class={ classes!("text-red-200") }>{ error }</p>
+                        }
                         <div id="preview-area">
                             { for self.files.iter().map(Self::view_file) }
                         </div>
@@ -250,10 +377,13 @@ impl Component for App {
                                         {"Tile selection"}
                                     </label>     
                                 </div>
-                                <div 
+                                <div
                                     id="tile-dropdown-wrapper"
-                                    class={ classes!("rounded-md","bg-white","focus:outline-none") } 
-                                    role="menu" 
+                                    class={ classes!(
+                                        "rounded-md","bg-white","focus:outline-none",
+                                        (!self.tile_dropdown_is_open).then_some("hidden")
+                                    ) }
+                                    role="menu"
                                     aria-orientation="vertical" 
                                     aria-labelledby="menu-button" 
                                     tabindex="-1"
@@ -263,24 +393,56 @@ impl Component for App {
                                         class="py-1" 
                                         role="none"
                                     >
-                                        <a href="#" class="text-gray-700 block px-4 py-2 text-sm" role="menuitem" tabindex="-1" id="menu-item-0">{"Circle"}</a>
-                                        <a href="#" class="text-gray-700 block px-4 py-2 text-sm" role="menuitem" tabindex="-1" id="menu-item-1">{"Triangle"}</a>
+                                        <a href="#" class="text-gray-700 block px-4 py-2 text-sm" role="menuitem" tabindex="-1" id="menu-item-0" onclick={circle_on_click}>{"Circle"}</a>
+                                        <a href="#" class="text-gray-700 block px-4 py-2 text-sm" role="menuitem" tabindex="-1" id="menu-item-1" onclick={triangle_on_click}>{"Triangle"}</a>
                                     </div>
                                 </div>
                                 <form method="POST" action="#" role="none" class={ classes!("my-20") }>
-                                    <button 
-                                        type="submit" 
-                                        class={ classes!("bg-white","text-gray-700","block","w-full","px-4","py-2","text-left","text-sm") } 
-                                        role="menuitem" 
-                                        tabindex="-1" 
+                                    <button
+                                        type="submit"
+                                        class={ classes!("bg-white","text-gray-700","block","w-full","px-4","py-2","text-left","text-sm") }
+                                        role="menuitem"
+                                        tabindex="-1"
                                         id="menu-item-3"
                                     >
                                         {"Apply"}
                                     </button>
                                 </form>
                             </li>
-                            <li>{""}</li>
-                            <li>{""}</li>
+                            <li
+                                id="grid-size-selection"
+                                class={ classes!("flex","flex-row","items-center") }
+                            >
+                                <label for="grid-size">{"Grid size"}</label>
+                                <input
+                                    id="grid-size"
+                                    type="number"
+                                    min="1"
+                                    value={self.grid_size.to_string()}
+                                    onchange={grid_size_on_change}
+                                />
+                            </li>
+                            <li
+                                id="dither-selection"
+                                class={ classes!("flex","flex-row","items-center") }
+                            >
+                                <label for="dither-enabled">{"Dither"}</label>
+                                <input
+                                    id="dither-enabled"
+                                    type="checkbox"
+                                    checked={self.dither_enabled}
+                                    onclick={dither_toggled_on_click}
+                                />
+                                <label for="dither-levels">{"Levels"}</label>
+                                <input
+                                    id="dither-levels"
+                                    type="number"
+                                    min="2"
+                                    max={self.dither_ceiling.to_string()}
+                                    value={self.dither_levels.to_string()}
+                                    onchange={dither_levels_on_change}
+                                />
+                            </li>
                         </ul>
                         <label for="generate">
                             <button 
@@ -292,10 +454,18 @@ impl Component for App {
                         </label>
                     </div>
 
-                    <div id="truchet-result" class={ classes!("flex","outline-dashed","outline-white") }>
+                    <div id="truchet-result" class={ classes!("flex","flex-col","outline-dashed","outline-white") }>
                         <p id="truchet-result-title">{ "Truchet!" }</p>
-                        <div id="truchet-output-area">
-                            
+                        <label for="png-resolution">{"PNG resolution (px)"}</label>
+                        <input
+                            id="png-resolution"
+                            type="number"
+                            min="1"
+                            value={self.png_resolution.to_string()}
+                            onchange={png_resolution_on_change}
+                        />
+                        <div id="truchet-output-area" class={ classes!("flex","flex-row","flex-wrap") }>
+                            { for self.results.iter().enumerate().map(|(index, tile)| self.view_result(ctx, index, tile)) }
                         </div>
                     </div>
                 </div>
@@ -305,6 +475,30 @@ impl Component for App {
 }
 
 impl App {
+    fn view_result(&self, ctx: &Context<Self>, index: usize, tile: &GeneratedTile) -> Html {
+        let download_svg_on_click = ctx.link().callback(move |event: MouseEvent| {
+            event.stop_propagation();
+            Msg::DownloadSvg(index)
+        });
+        let download_png_on_click = ctx.link().callback(move |event: MouseEvent| {
+            event.stop_propagation();
+            Msg::DownloadPng(index)
+        });
+
+        html! {
+            <div class="result-tile">
+                <p class="result-name">{ &tile.name }</p>
+                <div class="result-svg">
+                    { Html::from_html_unchecked(tile.svg_html.clone().into()) }
+                </div>
+                <div class={ classes!("result-downloads","flex","space-x-2") }>
+                    <button onclick={download_svg_on_click}>{"Download SVG"}</button>
+                    <button onclick={download_png_on_click}>{"Download PNG"}</button>
+                </div>
+            </div>
+        }
+    }
+
     fn view_file(file: &FileDetails) -> Html {
         html! {
             <div class="preview-tile">
@@ -322,8 +516,314 @@ impl App {
         }
     }
 
+    /// Reads the EXIF orientation tag (0x0112) out of a JPEG's APP1 segment.
+    /// Returns 1 (normal) for non-JPEG data or files with no EXIF block.
+    fn read_exif_orientation(data: &[u8]) -> u32 {
+        if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+            return 1;
+        }
+
+        let mut offset = 2;
+        while offset + 4 <= data.len() {
+            if data[offset] != 0xFF {
+                break;
+            }
+            let marker = data[offset + 1];
+            if marker == 0xDA {
+                break;
+            }
+            let segment_len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            if marker == 0xE1
+                && segment_len >= 8
+                && offset + 10 <= data.len()
+                && &data[offset + 4..offset + 10] == b"Exif\0\0"
+            {
+                let tiff_end = (offset + 2 + segment_len).clamp(offset + 10, data.len());
+                return Self::parse_tiff_orientation(&data[offset + 10..tiff_end]).unwrap_or(1);
+            }
+            offset += 2 + segment_len;
+        }
+        1
+    }
+
+    fn parse_tiff_orientation(tiff: &[u8]) -> Option<u32> {
+        if tiff.len() < 8 {
+            return None;
+        }
+        let little_endian = match &tiff[0..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let read_u16 = |b: &[u8]| {
+            if little_endian {
+                u16::from_le_bytes([b[0], b[1]])
+            } else {
+                u16::from_be_bytes([b[0], b[1]])
+            }
+        };
+        let read_u32 = |b: &[u8]| {
+            if little_endian {
+                u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+            } else {
+                u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+            }
+        };
+
+        let ifd_offset = read_u32(&tiff[4..8]) as usize;
+        let entries_offset = ifd_offset.checked_add(2)?;
+        if entries_offset > tiff.len() {
+            return None;
+        }
+        let entry_count = read_u16(&tiff[ifd_offset..entries_offset]) as usize;
+        for i in 0..entry_count {
+            let entry_span = i.checked_mul(12)?;
+            let entry_offset = entries_offset.checked_add(entry_span)?;
+            let entry_end = match entry_offset.checked_add(12) {
+                Some(end) => end,
+                None => break,
+            };
+            if entry_end > tiff.len() {
+                break;
+            }
+            let tag = read_u16(&tiff[entry_offset..entry_offset + 2]);
+            if tag == 0x0112 {
+                return Some(read_u16(&tiff[entry_offset + 8..entry_offset + 10]) as u32);
+            }
+        }
+        None
+    }
+
+    /// Applies the rotation/flip implied by an EXIF orientation value so the
+    /// pixels fed into `ImageAdapter` are upright regardless of how the
+    /// source camera wrote them.
+    fn apply_orientation(image: GrayImage, orientation: u32) -> GrayImage {
+        match orientation {
+            2 => image::imageops::flip_horizontal(&image),
+            3 => image::imageops::rotate180(&image),
+            4 => image::imageops::flip_vertical(&image),
+            5 => image::imageops::flip_horizontal(&image::imageops::rotate90(&image)),
+            6 => image::imageops::rotate90(&image),
+            7 => image::imageops::flip_horizontal(&image::imageops::rotate270(&image)),
+            8 => image::imageops::rotate270(&image),
+            _ => image,
+        }
+    }
+
+    /// Renders the chosen generator at a dense sweep of brightness values
+    /// on a single-cell grid and counts how many visually distinct tiles it
+    /// actually draws, so the dither level default/ceiling reflects what
+    /// the generator can render instead of a guessed constant.
+    fn tile_state_count(tile_kind: TileKind) -> u32 {
+        const SAMPLES: usize = 64;
+        let grid = Vec2::new(1, 1);
+        let mut distinct_tiles: Vec<Vec<u8>> = Vec::new();
+
+        for step in 0..=SAMPLES {
+            let brightness = step as f32 / SAMPLES as f32;
+            let sample = SolidImage { brightness };
+            let truchet = match tile_kind {
+                TileKind::Circle => truchet::truchet_image::generate(&sample, truchet::generator::circles(grid)),
+                TileKind::Triangle => truchet::truchet_image::generate(&sample, truchet::generator::triangles(grid)),
+            };
+            let doc = SVG::new().add(truchet.to_svg_node());
+            let mut data = vec![];
+            svg::write(&mut data, &doc).unwrap();
+            if !distinct_tiles.contains(&data) {
+                distinct_tiles.push(data);
+            }
+        }
+
+        (distinct_tiles.len() as u32).max(2)
+    }
+
+    /// Floyd-Steinberg error-diffusion dither: quantizes each pixel to one
+    /// of `levels` evenly spaced brightness steps and pushes the rounding
+    /// error onto its still-unvisited neighbors, so gradients band less
+    /// once the generator collapses each cell to a single tile state.
+    fn dither(image: GrayImage, levels: u32) -> GrayImage {
+        if levels < 2 {
+            return image;
+        }
+
+        let (width, height) = image.dimensions();
+        let levels = levels as f32;
+        let step = 255.0 / (levels - 1.0);
+        let mut buffer: Vec<f32> = image.pixels().map(|p| p.0[0] as f32).collect();
+
+        let add_error = |buffer: &mut Vec<f32>, x: i64, y: i64, amount: f32| {
+            if x >= 0 && x < width as i64 && y >= 0 && y < height as i64 {
+                let idx = (y as u32 * width + x as u32) as usize;
+                buffer[idx] = (buffer[idx] + amount).clamp(0.0, 255.0);
+            }
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let idx = (y * width + x) as usize;
+                let old = buffer[idx];
+                let quantized = (old / step).round().clamp(0.0, levels - 1.0) * step;
+                let error = old - quantized;
+                buffer[idx] = quantized;
+
+                add_error(&mut buffer, x as i64 + 1, y as i64, error * 7.0 / 16.0);
+                add_error(&mut buffer, x as i64 - 1, y as i64 + 1, error * 3.0 / 16.0);
+                add_error(&mut buffer, x as i64, y as i64 + 1, error * 5.0 / 16.0);
+                add_error(&mut buffer, x as i64 + 1, y as i64 + 1, error * 1.0 / 16.0);
+            }
+        }
+
+        GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([buffer[(y * width + x) as usize].round() as u8])
+        })
+    }
+
+    /// Drops any APP1 "Exif" segment from a JPEG so uploaded photos don't
+    /// carry camera/location metadata into previews or downloads.
+    fn strip_exif(data: Vec<u8>) -> Vec<u8> {
+        if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+            return data;
+        }
+
+        let mut result = Vec::with_capacity(data.len());
+        result.extend_from_slice(&data[0..2]);
+        let mut offset = 2;
+        while offset + 4 <= data.len() {
+            if data[offset] != 0xFF {
+                break;
+            }
+            let marker = data[offset + 1];
+            if marker == 0xDA {
+                result.extend_from_slice(&data[offset..]);
+                break;
+            }
+            let segment_len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+            let segment_end = (offset + 2 + segment_len).min(data.len());
+            let is_exif = marker == 0xE1
+                && offset + 10 <= data.len()
+                && &data[offset + 4..offset + 10] == b"Exif\0\0";
+            if !is_exif {
+                result.extend_from_slice(&data[offset..segment_end]);
+            }
+            offset = segment_end;
+        }
+        result
+    }
+
+    /// Runs the Truchet generator over a single uploaded file, producing
+    /// both the downloadable SVG bytes and the string used for inline
+    /// rendering.
+    fn generate_tile(
+        file: &FileDetails,
+        tile_kind: TileKind,
+        grid_size: usize,
+        dither: DitherSettings,
+    ) -> image::ImageResult<GeneratedTile> {
+        let image = image::load_from_memory(&file.data)?;
+        let luma = Self::apply_orientation(image.into_luma8(), file.orientation);
+        let luma = if dither.enabled {
+            Self::dither(luma, dither.levels)
+        } else {
+            luma
+        };
+        let grayscale = ImageAdapter::new(luma);
+        let grid = Vec2::new(grid_size, grid_size);
+        let truchet = match tile_kind {
+            TileKind::Circle => truchet::truchet_image::generate(&grayscale, truchet::generator::circles(grid)),
+            TileKind::Triangle => truchet::truchet_image::generate(&grayscale, truchet::generator::triangles(grid)),
+        };
+        let svg = truchet.to_svg_node();
+        let g = Group::new()
+            .set("transform", "scale(10 10)")
+            .add(svg);
+        let svg_doc = SVG::new()
+            .add(g)
+            .set("height", "500px")
+            .set("width", "500px");
+        let mut data = vec![];
+        svg::write(&mut data, &svg_doc).unwrap();
+        let svg_html = String::from_utf8(data.clone()).unwrap();
+
+        Ok(GeneratedTile {
+            name: file.name.clone(),
+            svg_doc: data,
+            svg_html,
+        })
+    }
+
+    fn download_svg(svg_bytes: &[u8]) {
+        let array = js_sys::Array::new();
+        array.push(&js_sys::Uint8Array::from(svg_bytes).into());
+        let blob_props = BlobPropertyBag::new();
+        blob_props.set_type("image/svg+xml");
+        let blob = Blob::new_with_u8_array_sequence_and_options(&array, &blob_props)
+            .expect("blob creation should succeed");
+        let url = Url::create_object_url_with_blob(&blob).expect("object url should be created");
+
+        Self::trigger_download(&url, "truchet.svg");
+
+        Url::revoke_object_url(&url).expect("object url should be revoked");
+    }
+
+    fn download_png(svg_bytes: &[u8], resolution: u32) {
+        let svg_data_url = format!(
+            "data:image/svg+xml;base64,{}",
+            STANDARD.encode(svg_bytes)
+        );
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let canvas: HtmlCanvasElement = document
+            .create_element("canvas")
+            .expect("canvas element should be created")
+            .dyn_into()
+            .expect("element should be a canvas");
+        canvas.set_width(resolution);
+        canvas.set_height(resolution);
+        let context: CanvasRenderingContext2d = canvas
+            .get_context("2d")
+            .expect("2d context should be available")
+            .expect("2d context should exist")
+            .dyn_into()
+            .expect("context should be CanvasRenderingContext2d");
+
+        let image = HtmlImageElement::new().expect("image element should be created");
+        let onload_canvas = canvas.clone();
+        let onload_image = image.clone();
+        let onload = Closure::once(move || {
+            context
+                .draw_image_with_html_image_element_and_dw_and_dh(
+                    &onload_image,
+                    0.0,
+                    0.0,
+                    onload_canvas.width() as f64,
+                    onload_canvas.height() as f64,
+                )
+                .expect("svg image should draw onto canvas");
+
+            if let Ok(data_url) = onload_canvas.to_data_url_with_type("image/png") {
+                Self::trigger_download(&data_url, "truchet.png");
+            }
+        });
+        image.set_onload(Some(onload.as_ref().unchecked_ref()));
+        onload.forget();
+        image.set_src(&svg_data_url);
+    }
+
+    fn trigger_download(url: &str, filename: &str) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let anchor: HtmlAnchorElement = document
+            .create_element("a")
+            .expect("anchor element should be created")
+            .dyn_into()
+            .expect("element should be an anchor");
+        anchor.set_href(url);
+        anchor.set_download(filename);
+        anchor.click();
+    }
+
     fn upload_files(files: Option<FileList>) -> Msg {
-        let mut result = Vec::new();
+        let mut accepted = Vec::new();
+        let mut rejected = 0;
 
         if let Some(files) = files {
             let files = js_sys::try_iter(&files)
@@ -331,12 +831,183 @@ impl App {
                 .unwrap()
                 .map(|v| web_sys::File::from(v.unwrap()))
                 .map(File::from);
-            result.extend(files);
+            for file in files {
+                if file.raw_mime_type().starts_with("image/") {
+                    accepted.push(file);
+                } else {
+                    rejected += 1;
+                }
+            }
         }
-        Msg::Files(result)
+
+        let error = (rejected > 0).then(|| {
+            format!(
+                "Skipped {} file{} that {} not a recognized image type",
+                rejected,
+                if rejected == 1 { "" } else { "s" },
+                if rejected == 1 { "was" } else { "were" }
+            )
+        });
+
+        Msg::Files(accepted, error)
     }
 }
 
 fn main() {
     yew::Renderer::<App>::new().render();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_with_app1(segment_len: u16, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        data.extend_from_slice(&segment_len.to_be_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn read_exif_orientation_defaults_to_normal_for_non_jpeg() {
+        assert_eq!(App::read_exif_orientation(b"not a jpeg"), 1);
+    }
+
+    #[test]
+    fn read_exif_orientation_does_not_panic_on_truncated_segment() {
+        // segment_len (6) covers only the two length bytes and "Exif", so
+        // the APP1 segment ends before the signature's trailing NULs and
+        // leaves no room for a TIFF header.
+        let data = jpeg_with_app1(6, b"Exif\0\0");
+        assert_eq!(App::read_exif_orientation(&data), 1);
+    }
+
+    #[test]
+    fn read_exif_orientation_reads_tag_from_well_formed_segment() {
+        let mut tiff = Vec::new();
+        tiff.extend_from_slice(b"II");
+        tiff.extend_from_slice(&42u16.to_le_bytes());
+        tiff.extend_from_slice(&8u32.to_le_bytes()); // IFD offset
+        tiff.extend_from_slice(&1u16.to_le_bytes()); // 1 entry
+        tiff.extend_from_slice(&0x0112u16.to_le_bytes()); // orientation tag
+        tiff.extend_from_slice(&3u16.to_le_bytes()); // type: SHORT
+        tiff.extend_from_slice(&1u32.to_le_bytes()); // count
+        tiff.extend_from_slice(&6u16.to_le_bytes()); // value: rotate 90 CW
+        tiff.extend_from_slice(&0u16.to_le_bytes()); // pad entry to 12 bytes
+
+        let mut body = b"Exif\0\0".to_vec();
+        body.extend_from_slice(&tiff);
+        let data = jpeg_with_app1((body.len() + 2) as u16, &body);
+
+        assert_eq!(App::read_exif_orientation(&data), 6);
+    }
+
+    #[test]
+    fn parse_tiff_orientation_reads_little_endian_tag() {
+        let mut tiff = Vec::new();
+        tiff.extend_from_slice(b"II");
+        tiff.extend_from_slice(&42u16.to_le_bytes());
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x0112u16.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&6u16.to_le_bytes());
+        tiff.extend_from_slice(&0u16.to_le_bytes());
+
+        assert_eq!(App::parse_tiff_orientation(&tiff), Some(6));
+    }
+
+    #[test]
+    fn parse_tiff_orientation_rejects_bad_header() {
+        assert_eq!(App::parse_tiff_orientation(b"not tiff"), None);
+    }
+
+    #[test]
+    fn apply_orientation_rotate_180_flips_both_axes() {
+        let image = GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 10 } else { 20 }]));
+        let rotated = App::apply_orientation(image, 3);
+        assert_eq!(rotated.get_pixel(0, 0).0[0], 20);
+        assert_eq!(rotated.get_pixel(1, 0).0[0], 10);
+    }
+
+    #[test]
+    fn apply_orientation_identity_for_unknown_value() {
+        let image = GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 10 } else { 20 }]));
+        let unchanged = App::apply_orientation(image.clone(), 9);
+        assert_eq!(unchanged.get_pixel(0, 0).0[0], 10);
+        assert_eq!(unchanged.get_pixel(1, 0).0[0], 20);
+    }
+
+    #[test]
+    fn strip_exif_removes_app1_but_keeps_other_segments() {
+        let mut data = vec![0xFF, 0xD8];
+        // APP1/Exif segment that should be stripped.
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&10u16.to_be_bytes());
+        data.extend_from_slice(b"Exif\0\0XX");
+        // A harmless APP0/JFIF segment that should survive.
+        data.extend_from_slice(&[0xFF, 0xE0]);
+        data.extend_from_slice(&7u16.to_be_bytes());
+        data.extend_from_slice(b"JFIF\0");
+        // Start-of-scan marker; nothing after it is inspected.
+        data.extend_from_slice(&[0xFF, 0xDA, 0xAA]);
+
+        let stripped = App::strip_exif(data);
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert!(stripped.windows(4).any(|w| w == b"JFIF"));
+    }
+
+    #[test]
+    fn strip_exif_leaves_non_jpeg_data_untouched() {
+        let data = b"not a jpeg".to_vec();
+        assert_eq!(App::strip_exif(data.clone()), data);
+    }
+
+    #[test]
+    fn dither_quantizes_every_pixel_to_one_of_two_levels() {
+        let image = GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 60 } else { 200 }]));
+        let dithered = App::dither(image, 2);
+        for pixel in dithered.pixels() {
+            assert!(pixel.0[0] == 0 || pixel.0[0] == 255);
+        }
+    }
+
+    #[test]
+    fn dither_is_a_no_op_below_two_levels() {
+        let image = GrayImage::from_fn(2, 1, |x, _| image::Luma([if x == 0 { 60 } else { 123 } ]));
+        let unchanged = App::dither(image.clone(), 1);
+        assert_eq!(unchanged.get_pixel(0, 0).0[0], 60);
+        assert_eq!(unchanged.get_pixel(1, 0).0[0], 123);
+    }
+
+    #[test]
+    fn dither_propagates_error_to_downstream_rows() {
+        // (0, 0) and (1, 0) both quantize down to 0, but the error they push
+        // down and diagonally onto (1, 1) lands it above the quantization
+        // midpoint even though 110 alone would round down to 0. If the
+        // neighbor offsets or weights in `dither` were wrong, (1, 1) would
+        // come out 0 instead.
+        let pixels = [[127u8, 0], [0, 110]];
+        let image = GrayImage::from_fn(2, 2, |x, y| image::Luma([pixels[y as usize][x as usize]]));
+        let dithered = App::dither(image, 2);
+
+        assert_eq!(dithered.get_pixel(0, 0).0[0], 0);
+        assert_eq!(dithered.get_pixel(1, 0).0[0], 0);
+        assert_eq!(dithered.get_pixel(0, 1).0[0], 0);
+        assert_eq!(dithered.get_pixel(1, 1).0[0], 255);
+    }
+
+    #[test]
+    fn tile_state_count_is_derived_from_the_generator() {
+        // The generator is sampled rather than guessed, so the only
+        // invariants we can assert without re-implementing the generator
+        // are that it never reports fewer than the 2 levels dithering
+        // needs, and that the same tile kind always samples the same way.
+        for tile_kind in [TileKind::Circle, TileKind::Triangle] {
+            let count = App::tile_state_count(tile_kind);
+            assert!(count >= 2);
+            assert_eq!(count, App::tile_state_count(tile_kind));
+        }
+    }
 }
\ No newline at end of file